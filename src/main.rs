@@ -14,16 +14,20 @@ use port_strategy::PortStrategy;
 mod benchmark;
 use benchmark::{Benchmark, NamedTimer};
 
+mod scripts;
+use scripts::{run_scripts, Script};
+
 use cidr_utils::cidr::IpCidr;
 use colorful::{Color, Colorful};
 use futures::executor::block_on;
 use rlimit::{getrlimit, setrlimit, Resource};
-use std::collections::HashMap;
+use serde::Serialize;
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::{prelude::*, BufReader};
 use std::net::{IpAddr, ToSocketAddrs};
 use std::path::Path;
-use std::process::Command;
 use std::time::Duration;
 use trust_dns_resolver::{config::*, Resolver};
 
@@ -34,6 +38,9 @@ extern crate dirs;
 const DEFAULT_FILE_DESCRIPTORS_LIMIT: rlimit::rlim = 8000;
 // Safest batch size based on experimentation
 const AVERAGE_BATCH_SIZE: rlimit::rlim = 3000;
+// Hard ceiling on concurrency regardless of what the ulimit permits, unless
+// overridden with --max-batch.
+const DEFAULT_MAX_BATCH_SIZE: rlimit::rlim = 65_535;
 
 #[macro_use]
 extern crate log;
@@ -50,15 +57,30 @@ fn main() {
     let config = Config::read();
     opts.merge(&config);
 
+    tui::set_color_enabled(color_enabled(&opts));
+    tui::set_json_mode(opts.json);
+
     debug!("Main() `opts` arguments are {:?}", opts);
 
-    if !opts.greppable && !opts.accessible {
+    if !opts.greppable && !opts.accessible && !opts.json {
         print_opening(&opts);
     }
 
-    let ips: Vec<IpAddr> = parse_addresses(&opts);
+    let resolver = build_resolver(&opts);
+    let excludes = build_exclude_list(&opts, &resolver);
+    let excluded_count = Cell::new(0usize);
+    let mut targets = address_stream(&opts, &resolver)
+        .filter(|ip| {
+            if excludes.is_excluded(ip) {
+                excluded_count.set(excluded_count.get() + 1);
+                false
+            } else {
+                true
+            }
+        })
+        .peekable();
 
-    if ips.is_empty() {
+    if targets.peek().is_none() {
         warning!(
             "No IPs could be resolved, aborting scan.",
             opts.greppable,
@@ -69,95 +91,124 @@ fn main() {
 
     let ulimit: rlimit::rlim = adjust_ulimit_size(&opts);
     let batch_size: u16 = infer_batch_size(&opts, ulimit);
-
-    let scanner = Scanner::new(
-        &ips,
-        batch_size,
-        Duration::from_millis(opts.timeout.into()),
-        opts.tries,
+    detail!(
+        format!("Using batch size {}", batch_size),
         opts.greppable,
-        PortStrategy::pick(opts.range, opts.ports, opts.scan_order),
-        opts.accessible,
+        opts.accessible
     );
-    debug!("Scanner finished building: {:?}", scanner);
 
+    // Pull targets out of the (lazy) stream one batch at a time instead of
+    // collecting it into a `Vec` up front, so a `/8` CIDR or a multi-million
+    // line host file keeps memory bounded by `batch_size` rather than by the
+    // number of targets.
+    let mut ports_per_ip = HashMap::new();
     let mut portscan_bench = NamedTimer::start("Portscan");
-    let scan_result = block_on(scanner.run());
-    portscan_bench.end();
-    benchmarks.push(portscan_bench);
+    loop {
+        let chunk: Vec<IpAddr> = targets.by_ref().take(batch_size.into()).collect();
+        if chunk.is_empty() {
+            break;
+        }
 
-    let mut ports_per_ip = HashMap::new();
+        let scanner = Scanner::new(
+            &chunk,
+            batch_size,
+            Duration::from_millis(opts.timeout.into()),
+            opts.tries,
+            opts.greppable,
+            PortStrategy::pick(opts.range, opts.ports.clone(), opts.scan_order),
+            opts.accessible,
+        );
+        debug!("Scanner finished building: {:?}", scanner);
 
-    for socket in scan_result {
-        ports_per_ip
-            .entry(socket.ip())
-            .or_insert_with(Vec::new)
-            .push(socket.port());
-    }
+        let scan_result = block_on(scanner.run());
 
-    for ip in ips {
-        if ports_per_ip.contains_key(&ip) {
-            continue;
+        for socket in scan_result {
+            ports_per_ip
+                .entry(socket.ip())
+                .or_insert_with(Vec::new)
+                .push(socket.port());
         }
 
-        // If we got here it means the IP was not found within the HashMap, this
-        // means the scan couldn't find any open ports for it.
+        for ip in chunk {
+            if ports_per_ip.contains_key(&ip) {
+                continue;
+            }
+
+            // If we got here it means the IP was not found within the HashMap, this
+            // means the scan couldn't find any open ports for it.
 
-        let x = format!("Looks like I didn't find any open ports for {:?}. This is usually caused by a high batch size.
-        \n*I used {} batch size, consider lowering it with {} or a comfortable number for your system.
-        \n Alternatively, increase the timeout if your ping is high. Rustscan -t 2000 for 2000 milliseconds (2s) timeout.\n",
-        ip,
-        opts.batch_size,
-        "'rustscan -b <batch_size> <ip address>'");
-        warning!(x, opts.greppable, opts.accessible);
+            let x = format!("Looks like I didn't find any open ports for {:?}. This is usually caused by a high batch size.
+            \n*I used {} batch size, consider lowering it with {} or a comfortable number for your system.
+            \n Alternatively, increase the timeout if your ping is high. Rustscan -t 2000 for 2000 milliseconds (2s) timeout.\n",
+            ip,
+            opts.batch_size,
+            "'rustscan -b <batch_size> <ip address>'");
+            warning!(x, opts.greppable, opts.accessible);
+        }
     }
+    portscan_bench.end();
+    benchmarks.push(portscan_bench);
 
-    let mut nmap_bench = NamedTimer::start("Nmap");
-    for (ip, ports) in ports_per_ip.iter_mut() {
-        let nmap_str_ports: Vec<String> = ports.into_iter().map(|port| port.to_string()).collect();
+    if excluded_count.get() > 0 {
+        detail!(
+            format!(
+                "Excluded {} target(s) via --exclude/--exclude-file.",
+                excluded_count.get()
+            ),
+            opts.greppable,
+            opts.accessible
+        );
+    }
 
-        // nmap port style is 80,443. Comma separated with no spaces.
-        let ports_str = nmap_str_ports.join(",");
+    if opts.json {
+        write_json_output(&opts, &ports_per_ip);
+    }
 
-        // if greppable mode is on nmap should not be spawned
+    let mut scripts_bench = NamedTimer::start("Scripts");
+    let scripts = build_scripts(&opts);
+    for (ip, ports) in ports_per_ip.iter_mut() {
+        // if greppable mode is on, JSON output was requested, or the user
+        // asked for no scripts, just print the ip -> [ports] mapping (or
+        // skip entirely for JSON, which was already written above) and move
+        // on instead of running scripts.
+        if opts.json {
+            continue;
+        }
         if opts.greppable || opts.no_nmap {
-            println!("{} -> [{}]", &ip, ports_str);
+            let ports_str: Vec<String> = ports.iter().map(|port| port.to_string()).collect();
+            println!("{} -> [{}]", &ip, ports_str.join(","));
             continue;
         }
-        detail!("Starting Nmap", opts.greppable, opts.accessible);
-
-        let addr = ip.to_string();
-        let user_nmap_args =
-            shell_words::split(&opts.command.join(" ")).expect("failed to parse nmap arguments");
-        let nmap_args = build_nmap_arguments(&addr, &ports_str, &user_nmap_args, ip.is_ipv6());
+        detail!("Starting Scripts", opts.greppable, opts.accessible);
 
-        output!(
-            format!(
-                "The Nmap command to be run is nmap {}\n",
-                &nmap_args.join(" ")
-            ),
-            opts.greppable.clone(),
-            opts.accessible.clone()
+        run_scripts(
+            &scripts,
+            &opts.script_tags,
+            *ip,
+            ports,
+            opts.greppable,
+            opts.accessible,
         );
-
-        // Runs the nmap command and spawns it as a process.
-        let mut child = Command::new("nmap")
-            .args(&nmap_args)
-            .spawn()
-            .expect("failed to execute nmap process");
-
-        child.wait().expect("failed to wait on nmap process");
     }
 
     // To use the runtime benchmark, run the process as: RUST_LOG=info ./rustscan
-    nmap_bench.end();
-    benchmarks.push(nmap_bench);
+    scripts_bench.end();
+    benchmarks.push(scripts_bench);
     rustscan_bench.end();
     benchmarks.push(rustscan_bench);
     debug!("Benchmarks raw {:?}", benchmarks);
     info!("{}", benchmarks.summary());
 }
 
+/// Whether ANSI styling should be emitted: off when `--no-color` is passed,
+/// when the `NO_COLOR` environment variable is set (per the convention that
+/// well-behaved CLI tools honor it, see https://no-color.org), or when
+/// `--format json` is requested, since JSON output is a machine-readable
+/// contract that must never carry ANSI escapes.
+fn color_enabled(opts: &Opts) -> bool {
+    !opts.no_color && !opts.json && std::env::var_os("NO_COLOR").is_none()
+}
+
 /// Prints the opening title of RustScan
 fn print_opening(opts: &Opts) {
     debug!("Printing opening");
@@ -166,12 +217,17 @@ fn print_opening(opts: &Opts) {
 | .-. \| {_} |.-._} } | |  .-._} }\     }/  /\  \| |\  |
 `-' `-'`-----'`----'  `-'  `----'  `---' `-'  `-'`-' `-'
 Faster Nmap scanning with Rust."#;
-    println!("{}", s.gradient(Color::Green).bold());
     let info = r#"________________________________________
 : https://discord.gg/GFrQsGy           :
 : https://github.com/RustScan/RustScan :
  --------------------------------------"#;
-    println!("{}", info.gradient(Color::Yellow).bold());
+    if color_enabled(opts) {
+        println!("{}", s.gradient(Color::Green).bold());
+        println!("{}", info.gradient(Color::Yellow).bold());
+    } else {
+        println!("{}", s);
+        println!("{}", info);
+    }
     funny_opening!();
 
     let mut home_dir = match dirs::home_dir() {
@@ -187,80 +243,240 @@ Faster Nmap scanning with Rust."#;
     );
 }
 
-/// Goes through all possible IP inputs (files or via argparsing)
-/// Parses the string(s) into IPs
+/// Goes through all possible IP inputs (files or via argparsing) and parses
+/// the string(s) into IPs.
+///
+/// This is a thin wrapper that collects [`address_stream`], minus the
+/// targets matched by `--exclude`/`--exclude-file`, kept around for the call
+/// sites (and tests) that want the full `Vec<IpAddr>` rather than a lazy
+/// stream.
 fn parse_addresses(input: &Opts) -> Vec<IpAddr> {
-    let mut ips: Vec<IpAddr> = Vec::new();
-    let mut unresolved_addresses: Vec<&str> = Vec::new();
-    let resolver =
-        &Resolver::new(ResolverConfig::cloudflare_tls(), ResolverOpts::default()).unwrap();
-
-    for address in &input.addresses {
-        match parse_address(address, resolver) {
-            Ok(parsed_ips) => {
-                if !parsed_ips.is_empty() {
-                    ips.extend(parsed_ips);
-                } else {
-                    unresolved_addresses.push(address);
-                }
+    let resolver = &build_resolver(input);
+
+    let excludes = build_exclude_list(input, resolver);
+    let excluded_count = Cell::new(0usize);
+
+    let ips: Vec<IpAddr> = address_stream(input, resolver)
+        .filter(|ip| {
+            if excludes.is_excluded(ip) {
+                excluded_count.set(excluded_count.get() + 1);
+                false
+            } else {
+                true
             }
-            _ => {
-                warning!(
-                    format!("Host {:?} could not be resolved.", address),
-                    input.greppable,
-                    input.accessible
-                );
+        })
+        .collect();
+
+    if excluded_count.get() > 0 {
+        detail!(
+            format!(
+                "Excluded {} target(s) via --exclude/--exclude-file.",
+                excluded_count.get()
+            ),
+            input.greppable,
+            input.accessible
+        );
+    }
+
+    ips
+}
+
+/// A denylist of IPs/CIDRs/hosts built from `--exclude`/`--exclude-file`,
+/// checked against every resolved target so sensitive hosts (gateways,
+/// monitoring boxes) never receive probes. CIDR entries are kept as
+/// `IpCidr` and tested with `contains()` rather than expanded into a
+/// `Vec<IpAddr>`, so excluding a large range stays cheap.
+struct ExcludeList {
+    ips: HashSet<IpAddr>,
+    cidrs: Vec<IpCidr>,
+}
+
+impl ExcludeList {
+    fn is_excluded(&self, ip: &IpAddr) -> bool {
+        self.ips.contains(ip) || self.cidrs.iter().any(|cidr| cidr.contains(*ip))
+    }
+}
+
+/// Parses `opts.exclude_addresses` and the contents of `opts.exclude_file`
+/// (if set) into an [`ExcludeList`], using the same `IpCidr::from_str`/
+/// resolver path used for targets so `--exclude` accepts IPs, CIDRs, or
+/// hostnames just like the positional targets do.
+fn build_exclude_list(opts: &Opts, resolver: &Resolver) -> ExcludeList {
+    let mut tokens = opts.exclude_addresses.clone();
+
+    if let Some(exclude_file) = &opts.exclude_file {
+        let path = Path::new(exclude_file);
+        if path.is_file() {
+            match std::fs::read_to_string(path) {
+                Ok(contents) => tokens.extend(contents.lines().map(str::to_owned)),
+                Err(_) => {
+                    warning!(
+                        format!("Exclude file {:?} could not be read.", exclude_file),
+                        opts.greppable,
+                        opts.accessible
+                    );
+                }
             }
+        } else {
+            warning!(
+                format!("Exclude file {:?} could not be read.", exclude_file),
+                opts.greppable,
+                opts.accessible
+            );
         }
     }
 
+    let mut ips = HashSet::new();
+    let mut cidrs = Vec::new();
+
+    for token in tokens.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+        if let Ok(cidr) = IpCidr::from_str(token) {
+            cidrs.push(cidr);
+        } else {
+            ips.extend(resolve_token(token, resolver));
+        }
+    }
+
+    ExcludeList { ips, cidrs }
+}
+
+/// Lazily yields every IP implied by `input.addresses`: CIDRs are expanded
+/// on the fly via `IpCidr::iter()` (already lazy), hostnames are resolved
+/// one at a time, and any address that doesn't parse as a host/IP/CIDR is
+/// tried as a file, streamed line by line rather than read fully into
+/// memory. This lets a single `/8` CIDR or a multi-million-line host file
+/// be scanned with bounded memory instead of materializing into a `Vec`
+/// before the scan even starts.
+fn address_stream<'a>(
+    input: &'a Opts,
+    resolver: &'a Resolver,
+) -> impl Iterator<Item = IpAddr> + 'a {
+    input
+        .addresses
+        .iter()
+        .flat_map(move |address| parse_address_stream(address, resolver, input))
+}
+
+/// Tries to resolve `address` as a CIDR (which also covers a bare IP), a
+/// DNS hostname via `resolver`, or a socket address via `to_socket_addrs`,
+/// in that order, lazily yielding every IP it implies.
+/// Yields nothing if none of those interpretations apply.
+fn resolve_token<'a>(address: &'a str, resolver: &'a Resolver) -> Box<dyn Iterator<Item = IpAddr> + 'a> {
+    if let Ok(cidr) = IpCidr::from_str(&address) {
+        return Box::new(cidr.iter());
+    }
+
+    // Try the configured resolver (system/--resolver) before falling back to
+    // `to_socket_addrs`, which performs its own lookup via the OS resolver
+    // and would otherwise silently bypass whatever resolver the user chose.
+    if let Ok(hosts) = resolve_ips_from_host(address, resolver) {
+        if !hosts.is_empty() {
+            return Box::new(hosts.into_iter());
+        }
+    }
+
+    if let Ok(mut iter) = format!("{}:{}", &address, 80).to_socket_addrs() {
+        if let Some(addr) = iter.next() {
+            return Box::new(std::iter::once(addr.ip()));
+        }
+    }
+
+    Box::new(std::iter::empty())
+}
+
+/// Given a string, lazily parse it as a host, IP address, CIDR, or (as a
+/// last resort) a file path containing one address per line. Call this
+/// everytime you have a possible IP_or_host.
+fn parse_address_stream<'a>(
+    address: &'a str,
+    resolver: &'a Resolver,
+    input: &'a Opts,
+) -> Box<dyn Iterator<Item = IpAddr> + 'a> {
+    let mut resolved = resolve_token(address, resolver).peekable();
+    if resolved.peek().is_some() {
+        return Box::new(resolved);
+    }
+
     // If we got to this point this can only be a file path or the wrong input.
-    for file_path in unresolved_addresses {
-        let file_path = Path::new(file_path);
+    let file_path = Path::new(address);
+    if !file_path.is_file() {
+        warning!(
+            format!("Host {:?} could not be resolved.", address),
+            input.greppable,
+            input.accessible
+        );
+        return Box::new(std::iter::empty());
+    }
 
-        if !file_path.is_file() {
+    match read_ips_from_file(file_path, resolver) {
+        Ok(ips) => Box::new(ips),
+        Err(_) => {
             warning!(
                 format!("Host {:?} could not be resolved.", file_path),
                 input.greppable,
                 input.accessible
             );
-
-            continue;
+            Box::new(std::iter::empty())
         }
+    }
+}
 
-        match read_ips_from_file(file_path, &resolver) {
-            Ok(x) => ips.extend(x),
-            _ => {
+/// Picks the `ResolverConfig` implied by `--resolver`/`opts.resolver`:
+/// `None` means "use the system resolver" (the `system`/unset case, handled
+/// separately by `build_resolver` since it reads `/etc/resolv.conf` rather
+/// than building a `ResolverConfig`), `Some` carries one of the named
+/// presets (`cloudflare`, `cloudflare-tls`, `google`, `quad9`) or a config
+/// built from an explicit comma-separated list of nameserver IPs. Kept
+/// separate from `build_resolver` so the selection logic can be tested
+/// without touching `/etc/resolv.conf` or constructing a real `Resolver`.
+fn resolver_preset(opts: &Opts) -> Option<ResolverConfig> {
+    match opts.resolver.as_deref() {
+        None | Some("system") => None,
+        Some("cloudflare") => Some(ResolverConfig::cloudflare()),
+        Some("cloudflare-tls") => Some(ResolverConfig::cloudflare_tls()),
+        Some("google") => Some(ResolverConfig::google()),
+        Some("quad9") => Some(ResolverConfig::quad9()),
+        Some(nameservers) => {
+            let ips: Vec<IpAddr> = nameservers
+                .split(',')
+                .filter_map(|s| s.trim().parse().ok())
+                .collect();
+
+            if ips.is_empty() {
                 warning!(
-                    format!("Host {:?} could not be resolved.", file_path),
-                    input.greppable,
-                    input.accessible
+                    format!(
+                        "Could not parse --resolver value {:?}, falling back to the system resolver.",
+                        nameservers
+                    ),
+                    opts.greppable,
+                    opts.accessible
                 );
+                return None;
             }
+
+            Some(ResolverConfig::from_parts(
+                None,
+                vec![],
+                NameServerConfigGroup::from_ips_clear(&ips, 53),
+            ))
         }
     }
-
-    ips
 }
 
-/// Given a string, parse it as an host, IP address, or CIDR.
-/// This allows us to pass files as hosts or cidr or IPs easily
-/// Call this everytime you have a possible IP_or_host
-fn parse_address(address: &str, resolver: &Resolver) -> Result<Vec<IpAddr>, std::io::Error> {
-    let mut ips: Vec<IpAddr> = Vec::new();
-
-    match IpCidr::from_str(&address) {
-        Ok(cidr) => cidr.iter().for_each(|ip| ips.push(ip)),
-        _ => match format!("{}:{}", &address, 80).to_socket_addrs() {
-            Ok(mut iter) => ips.push(iter.nth(0).unwrap().ip()),
-            _ => match resolve_ips_from_host(address, resolver) {
-                Ok(hosts) => ips.extend(hosts),
-                _ => (),
-            },
-        },
-    };
-
-    Ok(ips)
+/// Builds the `Resolver` used for every hostname lookup, honoring
+/// `--resolver`/`opts.resolver` so internal/split-horizon DNS setups don't
+/// get silently routed through Cloudflare. Accepts `system` (the default,
+/// reading `/etc/resolv.conf`), the named presets `cloudflare`,
+/// `cloudflare-tls`, `google`, `quad9`, or an explicit comma-separated list
+/// of nameserver IPs. If the system configuration cannot be read, falls
+/// back to Cloudflare without a warning.
+fn build_resolver(opts: &Opts) -> Resolver {
+    match resolver_preset(opts) {
+        Some(config) => Resolver::new(config, ResolverOpts::default()).unwrap(),
+        None => Resolver::from_system_conf().unwrap_or_else(|_| {
+            Resolver::new(ResolverConfig::cloudflare(), ResolverOpts::default()).unwrap()
+        }),
+    }
 }
 
 /// Uses DNS to get the IPS assiocated with host
@@ -272,51 +488,86 @@ fn resolve_ips_from_host(source: &str, resolver: &Resolver) -> Result<Vec<IpAddr
 }
 
 #[cfg(not(tarpaulin_include))]
-/// Parses an input file of IPs and uses those
-fn read_ips_from_file(
-    ips: &std::path::Path,
-    resolver: &Resolver,
-) -> Result<Vec<std::net::IpAddr>, std::io::Error> {
+/// Streams an input file of IPs/hosts/CIDRs line by line rather than
+/// collecting the whole file into memory, flat-mapping each line's parse
+/// result into the returned iterator.
+fn read_ips_from_file<'a>(
+    ips: &'a std::path::Path,
+    resolver: &'a Resolver,
+) -> Result<impl Iterator<Item = IpAddr> + 'a, std::io::Error> {
     let file = File::open(ips)?;
     let reader = BufReader::new(file);
 
-    let mut ips: Vec<std::net::IpAddr> = Vec::new();
-
-    for address_line in reader.lines() {
-        match address_line {
-            Ok(address) => match parse_address(&address, resolver) {
-                Ok(result) => ips.extend(result),
-                Err(e) => {
-                    debug!("{} is not a valid IP or host", e);
-                }
-            },
+    Ok(reader.lines().flat_map(move |address_line| {
+        let iter: Box<dyn Iterator<Item = IpAddr>> = match address_line {
+            Ok(address) => Box::new(resolve_token(&address, resolver).collect::<Vec<_>>().into_iter()),
             Err(_) => {
                 debug!("Line in file is not valid");
+                Box::new(std::iter::empty())
             }
-        }
-    }
-    Ok(ips)
+        };
+        iter
+    }))
 }
 
-#[cfg(not(tarpaulin_include))]
-fn build_nmap_arguments<'a>(
-    addr: &'a str,
-    ports: &'a str,
-    user_args: &'a Vec<String>,
-    is_ipv6: bool,
-) -> Vec<&'a str> {
-    let mut arguments: Vec<&str> = user_args.iter().map(AsRef::as_ref).collect();
-    arguments.push("-vvv");
+/// A single scan result record, serialized as `{"ip": "...", "ports": [...],
+/// "status": "open"}` for `--format json`, so downstream tooling can
+/// consume RustScan output reliably instead of scraping the `ip -> [ports]`
+/// greppable string.
+#[derive(Serialize)]
+struct ScanRecord {
+    ip: IpAddr,
+    ports: Vec<u16>,
+    status: &'static str,
+}
 
-    if is_ipv6 {
-        arguments.push("-6");
+/// Serializes `ports_per_ip` as a JSON array and writes it to `opts.output`
+/// if set, otherwise to stdout.
+fn write_json_output(opts: &Opts, ports_per_ip: &HashMap<IpAddr, Vec<u16>>) {
+    let records: Vec<ScanRecord> = ports_per_ip
+        .iter()
+        .map(|(ip, ports)| ScanRecord {
+            ip: *ip,
+            ports: ports.clone(),
+            status: "open",
+        })
+        .collect();
+
+    let json = match serde_json::to_string(&records) {
+        Ok(json) => json,
+        Err(e) => {
+            warning!(
+                format!("Failed to serialize results as JSON: {}", e),
+                opts.greppable,
+                opts.accessible
+            );
+            return;
+        }
+    };
+
+    match &opts.output {
+        Some(path) => {
+            if let Err(e) = std::fs::write(path, json) {
+                warning!(
+                    format!("Failed to write JSON output to {:?}: {}", path, e),
+                    opts.greppable,
+                    opts.accessible
+                );
+            }
+        }
+        None => println!("{}", json),
     }
+}
 
-    arguments.push("-p");
-    arguments.push(ports);
-    arguments.push(addr);
+/// Builds the list of scripts to run after the scan: the user's configured
+/// `[[scripts]]` entries (from `.rustscan.toml`/`--scripts`), falling back to
+/// the built-in nmap invocation when none were configured.
+fn build_scripts(opts: &Opts) -> Vec<Script> {
+    if !opts.scripts.is_empty() {
+        return opts.scripts.clone();
+    }
 
-    arguments
+    vec![Script::default_nmap(&opts.command)]
 }
 
 fn adjust_ulimit_size(opts: &Opts) -> rlimit::rlim {
@@ -375,17 +626,46 @@ fn infer_batch_size(opts: &Opts, ulimit: rlimit::rlim) -> u16 {
     // When the ulimit is higher than the batch size let the user know that the
     // batch size can be increased unless they specified the ulimit themselves.
     else if ulimit + 2 > batch_size && (opts.ulimit.is_none()) {
-        detail!(format!("File limit higher than batch size. Can increase speed by increasing batch size '-b {}'.", ulimit - 100), 
+        detail!(format!("File limit higher than batch size. Can increase speed by increasing batch size '-b {}'.", ulimit - 100),
             opts.greppable, opts.accessible);
     }
 
+    // Regardless of what the ulimit permits, never launch more concurrency
+    // than the configured ceiling: a system reporting a huge file descriptor
+    // limit shouldn't be allowed to flood the network stack.
+    let max_batch_size: rlimit::rlim = batch_size_ceiling(opts).into();
+    if batch_size > max_batch_size {
+        detail!(
+            format!(
+                "Clamping batch size from {} to the configured ceiling of {} (--max-batch to raise it).",
+                batch_size, max_batch_size
+            ),
+            opts.greppable,
+            opts.accessible
+        );
+        batch_size = max_batch_size;
+    }
+
     batch_size as u16
 }
 
+/// The hard ceiling on concurrency, applied after the ulimit adjustment in
+/// `infer_batch_size`: `--max-batch` if given, `DEFAULT_MAX_BATCH_SIZE`
+/// otherwise. The batch size is not tuned by probing.
+fn batch_size_ceiling(opts: &Opts) -> u16 {
+    opts.max_batch.unwrap_or(DEFAULT_MAX_BATCH_SIZE as u16)
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{adjust_ulimit_size, infer_batch_size, parse_addresses, print_opening, Opts};
-    use std::net::Ipv4Addr;
+    use crate::{
+        adjust_ulimit_size, batch_size_ceiling, build_exclude_list, build_resolver,
+        infer_batch_size, parse_addresses, print_opening, resolver_preset, Opts, ScanRecord,
+        DEFAULT_MAX_BATCH_SIZE,
+    };
+    use std::collections::HashMap;
+    use std::net::{IpAddr, Ipv4Addr};
+    use trust_dns_resolver::config::ResolverConfig;
 
     #[test]
     fn batch_size_lowered() {
@@ -463,11 +743,16 @@ mod tests {
 
     #[test]
     fn parse_correct_host_addresses() {
+        // A hostname now expands to every A/AAAA record the configured
+        // resolver returns (so a scan actually probes every address behind
+        // it), not just the first one `to_socket_addrs` happened to pick, so
+        // this only asserts it resolves to at least one address rather than
+        // exactly one.
         let mut opts = Opts::default();
         opts.addresses = vec!["google.com".to_owned()];
         let ips = parse_addresses(&opts);
 
-        assert_eq!(ips.len(), 1);
+        assert!(!ips.is_empty());
     }
 
     #[test]
@@ -513,4 +798,95 @@ mod tests {
         let ips = parse_addresses(&opts);
         assert_eq!(ips.len(), 0);
     }
+
+    #[test]
+    fn exclude_list_filters_exact_ip_and_cidr_matches() {
+        let mut opts = Opts::default();
+        opts.exclude_addresses = vec!["10.0.0.5".to_owned(), "192.168.1.0/24".to_owned()];
+        let resolver = build_resolver(&opts);
+        let excludes = build_exclude_list(&opts, &resolver);
+
+        assert!(excludes.is_excluded(&IpAddr::V4(Ipv4Addr::new(10, 0, 0, 5))));
+        assert!(excludes.is_excluded(&IpAddr::V4(Ipv4Addr::new(192, 168, 1, 42))));
+        assert!(!excludes.is_excluded(&IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8))));
+    }
+
+    #[test]
+    fn resolver_preset_selects_named_presets() {
+        let mut opts = Opts::default();
+
+        opts.resolver = None;
+        assert!(resolver_preset(&opts).is_none());
+
+        opts.resolver = Some("system".to_owned());
+        assert!(resolver_preset(&opts).is_none());
+
+        opts.resolver = Some("google".to_owned());
+        let google = resolver_preset(&opts).unwrap();
+        assert_eq!(google.name_servers(), ResolverConfig::google().name_servers());
+
+        opts.resolver = Some("quad9".to_owned());
+        let quad9 = resolver_preset(&opts).unwrap();
+        assert_eq!(quad9.name_servers(), ResolverConfig::quad9().name_servers());
+    }
+
+    #[test]
+    fn resolver_preset_parses_custom_nameserver_list() {
+        let mut opts = Opts::default();
+        opts.resolver = Some("1.1.1.1,1.0.0.1".to_owned());
+
+        let config = resolver_preset(&opts).unwrap();
+        let ips: Vec<IpAddr> = config.name_servers().iter().map(|ns| ns.socket_addr.ip()).collect();
+
+        assert!(ips.contains(&IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1))));
+        assert!(ips.contains(&IpAddr::V4(Ipv4Addr::new(1, 0, 0, 1))));
+    }
+
+    #[test]
+    fn resolver_preset_falls_back_to_system_on_unparseable_list() {
+        let mut opts = Opts::default();
+        opts.resolver = Some("not-an-ip".to_owned());
+
+        assert!(resolver_preset(&opts).is_none());
+    }
+
+    #[test]
+    fn scan_record_serializes_open_ports() {
+        let mut ports_per_ip = HashMap::new();
+        ports_per_ip.insert(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), vec![22, 80]);
+
+        let records: Vec<ScanRecord> = ports_per_ip
+            .iter()
+            .map(|(ip, ports)| ScanRecord {
+                ip: *ip,
+                ports: ports.clone(),
+                status: "open",
+            })
+            .collect();
+        let json = serde_json::to_string(&records).unwrap();
+
+        assert!(json.contains("\"ip\":\"127.0.0.1\""));
+        assert!(json.contains("\"ports\":[22,80]"));
+        assert!(json.contains("\"status\":\"open\""));
+    }
+
+    #[test]
+    fn max_batch_clamps_batch_size_below_the_ceiling() {
+        let mut opts = Opts::default();
+        opts.batch_size = 50_000;
+        opts.max_batch = Some(1_000);
+
+        // A huge ulimit would otherwise leave the requested 50_000 in place,
+        // past max_batch; the configured ceiling must win regardless.
+        let batch_size = infer_batch_size(&opts, 1_000_000);
+
+        assert_eq!(batch_size, 1_000);
+    }
+
+    #[test]
+    fn max_batch_defaults_to_the_hard_ceiling_when_unset() {
+        let opts = Opts::default();
+
+        assert_eq!(batch_size_ceiling(&opts), DEFAULT_MAX_BATCH_SIZE as u16);
+    }
 }
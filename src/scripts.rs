@@ -0,0 +1,208 @@
+use crate::warning;
+use std::net::IpAddr;
+use std::process::Command;
+
+/// A user-declared post-scan command, modeled loosely on Erlang's
+/// `open_port({spawn, Command}, ...)`: once RustScan has a set of open
+/// ports for an IP, any `Script` whose `tags` intersect the caller's
+/// requested tags gets its `call_format` expanded and spawned.
+///
+/// `call_format` may reference `{{ip}}`, `{{ports}}` (joined with
+/// `ports_separator`, a comma by default, nmap style) and `{{ports_space}}`
+/// (space-joined); RustScan substitutes these
+/// before handing the result to `shell_words::split`.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct Script {
+    pub tag: Option<String>,
+    pub call_format: String,
+    #[serde(default = "default_ports_separator")]
+    pub ports_separator: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+fn default_ports_separator() -> String {
+    ",".to_owned()
+}
+
+impl Script {
+    /// The built-in nmap invocation, used when the user has not configured
+    /// any `[[scripts]]` entries. Mirrors the hardcoded behaviour this
+    /// subsystem replaces.
+    pub fn default_nmap(user_args: &[String]) -> Self {
+        let mut call_format = "nmap -vvv{{v6}}".to_owned();
+        if !user_args.is_empty() {
+            call_format.push(' ');
+            call_format.push_str(&user_args.join(" "));
+        }
+        call_format.push_str(" -p {{ports}} {{ip}}");
+
+        Self {
+            tag: Some("default".to_owned()),
+            call_format,
+            ports_separator: default_ports_separator(),
+            tags: Vec::new(),
+        }
+    }
+
+    /// Whether this script should run for the given set of requested tags.
+    /// A script with no tags of its own matches any (or no) request.
+    pub fn matches(&self, requested_tags: &[String]) -> bool {
+        if self.tags.is_empty() || requested_tags.is_empty() {
+            return true;
+        }
+        self.tags.iter().any(|t| requested_tags.contains(t))
+    }
+
+    /// Expands `{{ip}}`, `{{ports}}`, `{{ports_space}}` and `{{v6}}` in
+    /// `call_format`. `{{v6}}` is only ever emitted by `default_nmap`
+    /// (" -6" for an IPv6 target, empty otherwise) so the built-in nmap
+    /// invocation keeps matching the hardcoded `-6` behaviour it replaced;
+    /// user-supplied scripts that don't reference it are unaffected.
+    pub fn render(&self, ip: IpAddr, ports: &[u16]) -> String {
+        let ports_joined = ports
+            .iter()
+            .map(|p| p.to_string())
+            .collect::<Vec<String>>()
+            .join(&self.ports_separator);
+        let ports_space = ports
+            .iter()
+            .map(|p| p.to_string())
+            .collect::<Vec<String>>()
+            .join(" ");
+        let v6_flag = if ip.is_ipv6() { " -6" } else { "" };
+
+        self.call_format
+            .replace("{{ip}}", &ip.to_string())
+            .replace("{{ports_space}}", &ports_space)
+            .replace("{{ports}}", &ports_joined)
+            .replace("{{v6}}", v6_flag)
+    }
+
+    /// Renders, splits with `shell_words` and spawns the result, waiting for
+    /// it to finish. A non-zero exit is surfaced to the caller instead of
+    /// being silently swallowed; a spawn failure is returned as an `Err`
+    /// rather than panicking so one broken script doesn't take down the
+    /// rest of the run.
+    pub fn run(&self, ip: IpAddr, ports: &[u16]) -> Result<std::process::ExitStatus, String> {
+        let rendered = self.render(ip, ports);
+        let args = shell_words::split(&rendered)
+            .map_err(|e| format!("failed to parse script command {:?}: {}", rendered, e))?;
+
+        let (program, rest) = args
+            .split_first()
+            .ok_or_else(|| format!("script command {:?} is empty", rendered))?;
+
+        let mut child = Command::new(program)
+            .args(rest)
+            .spawn()
+            .map_err(|e| format!("failed to execute {:?}: {}", program, e))?;
+
+        child
+            .wait()
+            .map_err(|e| format!("failed to wait on {:?}: {}", program, e))
+    }
+}
+
+/// Runs every script matching `requested_tags` against `(ip, ports)`,
+/// logging a `warning!` for each one that fails to spawn or exits non-zero
+/// instead of aborting the remaining scripts/targets.
+pub fn run_scripts(
+    scripts: &[Script],
+    requested_tags: &[String],
+    ip: IpAddr,
+    ports: &[u16],
+    greppable: bool,
+    accessible: bool,
+) {
+    for script in scripts.iter().filter(|s| s.matches(requested_tags)) {
+        match script.run(ip, ports) {
+            Ok(status) if !status.success() => {
+                warning!(
+                    format!("Script {:?} for {} exited with {}", script.tag, ip, status),
+                    greppable,
+                    accessible
+                );
+            }
+            Ok(_) => {}
+            Err(e) => {
+                warning!(
+                    format!("Script {:?} for {}: {}", script.tag, ip, e),
+                    greppable,
+                    accessible
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+    fn script(call_format: &str, tags: Vec<&str>) -> Script {
+        Script {
+            tag: Some("test".to_owned()),
+            call_format: call_format.to_owned(),
+            ports_separator: default_ports_separator(),
+            tags: tags.into_iter().map(str::to_owned).collect(),
+        }
+    }
+
+    #[test]
+    fn render_substitutes_ip_and_ports() {
+        let s = script("nmap -p {{ports}} {{ip}}", vec![]);
+        let ip: IpAddr = Ipv4Addr::new(127, 0, 0, 1).into();
+
+        assert_eq!(s.render(ip, &[22, 80]), "nmap -p 22,80 127.0.0.1");
+    }
+
+    #[test]
+    fn render_substitutes_ports_space_and_respects_custom_separator() {
+        let mut s = script("nmap -p {{ports}} --open {{ports_space}} {{ip}}", vec![]);
+        s.ports_separator = "-".to_owned();
+        let ip: IpAddr = Ipv4Addr::new(10, 0, 0, 1).into();
+
+        assert_eq!(
+            s.render(ip, &[22, 80]),
+            "nmap -p 22-80 --open 22 80 10.0.0.1"
+        );
+    }
+
+    #[test]
+    fn render_v6_flag_is_empty_for_ipv4_and_set_for_ipv6() {
+        let s = script("nmap -vvv{{v6}} -p {{ports}} {{ip}}", vec![]);
+        let v4: IpAddr = Ipv4Addr::new(127, 0, 0, 1).into();
+        let v6: IpAddr = Ipv6Addr::LOCALHOST.into();
+
+        assert_eq!(s.render(v4, &[80]), "nmap -vvv -p 80 127.0.0.1");
+        assert_eq!(s.render(v6, &[80]), "nmap -vvv -6 -p 80 ::1");
+    }
+
+    #[test]
+    fn matches_is_permissive_when_either_side_is_untagged() {
+        let untagged = script("echo {{ip}}", vec![]);
+        assert!(untagged.matches(&["custom".to_owned()]));
+        assert!(untagged.matches(&[]));
+
+        let tagged = script("echo {{ip}}", vec!["custom"]);
+        assert!(tagged.matches(&[]));
+    }
+
+    #[test]
+    fn matches_requires_tag_overlap_when_both_sides_are_tagged() {
+        let tagged = script("echo {{ip}}", vec!["custom"]);
+        assert!(tagged.matches(&["custom".to_owned()]));
+        assert!(!tagged.matches(&["other".to_owned()]));
+    }
+
+    #[test]
+    fn default_nmap_includes_v6_flag_and_user_args() {
+        let args = vec!["-A".to_owned()];
+        let s = Script::default_nmap(&args);
+
+        let v6: IpAddr = Ipv6Addr::LOCALHOST.into();
+        assert_eq!(s.render(v6, &[443]), "nmap -vvv -6 -A -p 443 ::1");
+    }
+}
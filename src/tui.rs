@@ -0,0 +1,85 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Flipped once in `main()` from `color_enabled(&opts)` so the
+/// `output!`/`detail!`/`warning!` macros below can short-circuit
+/// gradient/bold styling without threading an `Opts` reference through
+/// every call site that only has `greppable`/`accessible` to hand.
+static COLOR_ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Flipped once in `main()` from `opts.json` so the macros below can also
+/// short-circuit on JSON mode without threading an `Opts` reference through
+/// every call site: `--format json` is a machine-readable stdout contract,
+/// so none of these human-facing lines may interleave with it.
+static JSON_MODE: AtomicBool = AtomicBool::new(false);
+
+pub fn set_color_enabled(enabled: bool) {
+    COLOR_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn color_enabled() -> bool {
+    COLOR_ENABLED.load(Ordering::Relaxed)
+}
+
+pub fn set_json_mode(enabled: bool) {
+    JSON_MODE.store(enabled, Ordering::Relaxed);
+}
+
+pub fn json_mode() -> bool {
+    JSON_MODE.load(Ordering::Relaxed)
+}
+
+/// Prints an informational line, skipped entirely in greppable/accessible/
+/// JSON mode. Styled green when colors are enabled, plain otherwise.
+#[macro_export]
+macro_rules! output {
+    ($string:expr, $greppable:expr, $accessible:expr) => {
+        if !$greppable && !$accessible && !$crate::tui::json_mode() {
+            if $crate::tui::color_enabled() {
+                use colorful::Colorful;
+                println!("{}", $string.to_string().green());
+            } else {
+                println!("{}", $string.to_string());
+            }
+        }
+    };
+}
+
+/// Prints a detail line, skipped in greppable/JSON mode. Styled yellow when
+/// colors are enabled, plain otherwise.
+#[macro_export]
+macro_rules! detail {
+    ($string:expr, $greppable:expr, $accessible:expr) => {
+        let _ = $accessible;
+        if !$greppable && !$crate::tui::json_mode() {
+            if $crate::tui::color_enabled() {
+                use colorful::Colorful;
+                println!("{}", $string.to_string().yellow());
+            } else {
+                println!("{}", $string.to_string());
+            }
+        }
+    };
+}
+
+/// Prints a warning line, skipped in greppable/JSON mode. Styled red when
+/// colors are enabled, plain otherwise.
+#[macro_export]
+macro_rules! warning {
+    ($string:expr, $greppable:expr, $accessible:expr) => {
+        let _ = $accessible;
+        if !$greppable && !$crate::tui::json_mode() {
+            if $crate::tui::color_enabled() {
+                use colorful::Colorful;
+                println!("{}", $string.to_string().red());
+            } else {
+                println!("{}", $string.to_string());
+            }
+        }
+    };
+}
+
+/// Opening-banner quip hook. Expands to nothing: no quip is printed.
+#[macro_export]
+macro_rules! funny_opening {
+    () => {};
+}